@@ -1,14 +1,115 @@
-use chrono::{DateTime, FixedOffset, Local};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate};
 use eframe::egui;
 use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::Direction;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 const NOTES_DIR: &str = "notes";
 
+/// Window during which a filesystem event for a path the app itself just wrote
+/// is treated as an echo and ignored, so our own saves don't trigger a reload.
+const WRITE_DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// Task priority, serialized as the string `Low`/`Medium`/`High`.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    /// High→Low ordering weight, used by `SortMode::Priority`.
+    fn rank(self) -> u8 {
+        match self {
+            Priority::Low => 1,
+            Priority::Medium => 2,
+            Priority::High => 3,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "low" => Some(Priority::Low),
+            "medium" => Some(Priority::Medium),
+            "high" => Some(Priority::High),
+            _ => None,
+        }
+    }
+
+    /// Cycle Low→Medium→High→Low for the "bump priority" button.
+    fn next(self) -> Self {
+        match self {
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High => Priority::Low,
+        }
+    }
+}
+
+/// A single logged stretch of work on a note, normalized so minutes < 60.
+#[derive(Clone, Serialize, Deserialize)]
+struct TimeEntry {
+    #[serde(with = "yaml_date")]
+    logged_date: NaiveDate,
+    hours: u16,
+    minutes: u16,
+}
+
+/// Serialize `NaiveDate` as a plain `YYYY-MM-DD` string, matching the way the
+/// rest of the frontmatter stores dates and avoiding chrono's optional `serde`
+/// feature.
+mod yaml_date {
+    use chrono::NaiveDate;
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%Y-%m-%d";
+
+    pub fn serialize<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&date.format(FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        NaiveDate::parse_from_str(&raw, FORMAT).map_err(serde::de::Error::custom)
+    }
+}
+
+impl TimeEntry {
+    fn new(logged_date: NaiveDate, hours: u16, minutes: u16) -> Self {
+        let hours = hours + minutes / 60;
+        let minutes = minutes % 60;
+        Self {
+            logged_date,
+            hours,
+            minutes,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 struct NoteFrontmatter {
     id: String,
@@ -16,6 +117,10 @@ struct NoteFrontmatter {
     tags: Vec<String>,
     created_at: String,
     updated_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    time_entries: Vec<TimeEntry>,
 }
 
 #[derive(Clone)]
@@ -28,12 +133,100 @@ struct Note {
 #[derive(Default)]
 struct DraftNote {
     body: String,
+    /// Time entries carried for the note being edited. Loaded from the note's
+    /// frontmatter on selection and appended to by the "Log time" button, so
+    /// each entry keeps its original `logged_date` across saves.
+    time_entries: Vec<TimeEntry>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum SortMode {
     Recent,
     Title,
+    Priority,
+}
+
+/// Directed graph of `[[wiki-link]]` references between notes. Nodes are note
+/// titles; an edge `a -> b` means note `a`'s body links to note `b`.
+#[derive(Default)]
+struct LinkGraph {
+    graph: DiGraph<String, ()>,
+    nodes: BTreeMap<String, NodeIndex>,
+}
+
+impl LinkGraph {
+    fn build(notes: &[Note]) -> Self {
+        let mut graph = DiGraph::new();
+        let mut nodes: BTreeMap<String, NodeIndex> = BTreeMap::new();
+        for note in notes {
+            nodes
+                .entry(note.meta.title.clone())
+                .or_insert_with(|| graph.add_node(note.meta.title.clone()));
+        }
+        for note in notes {
+            let source = nodes[&note.meta.title];
+            for target in parse_links(&note.body) {
+                if let Some(&dest) = nodes.get(&target) {
+                    graph.add_edge(source, dest, ());
+                }
+            }
+        }
+        Self { graph, nodes }
+    }
+
+    fn neighbors(&self, title: &str, direction: Direction) -> Vec<String> {
+        let mut titles: Vec<String> = self
+            .nodes
+            .get(title)
+            .into_iter()
+            .flat_map(|&idx| self.graph.neighbors_directed(idx, direction))
+            .map(|idx| self.graph[idx].clone())
+            .collect();
+        titles.sort();
+        titles.dedup();
+        titles
+    }
+
+    fn outbound(&self, title: &str) -> Vec<String> {
+        self.neighbors(title, Direction::Outgoing)
+    }
+
+    fn inbound(&self, title: &str) -> Vec<String> {
+        self.neighbors(title, Direction::Incoming)
+    }
+
+    /// Links in `body` whose target title has no matching note.
+    fn broken_links(&self, body: &str) -> Vec<String> {
+        let mut broken: Vec<String> = parse_links(body)
+            .into_iter()
+            .filter(|target| !self.nodes.contains_key(target))
+            .collect();
+        broken.sort();
+        broken.dedup();
+        broken
+    }
+
+    /// Notes with neither inbound nor outbound links.
+    fn orphans(&self) -> Vec<String> {
+        let mut orphans: Vec<String> = self
+            .graph
+            .node_indices()
+            .filter(|&idx| {
+                self.graph
+                    .neighbors_directed(idx, Direction::Outgoing)
+                    .next()
+                    .is_none()
+                    && self
+                        .graph
+                        .neighbors_directed(idx, Direction::Incoming)
+                        .next()
+                        .is_none()
+            })
+            .map(|idx| self.graph[idx].clone())
+            .collect();
+        orphans.sort();
+        orphans
+    }
 }
 
 struct NotesApp {
@@ -44,10 +237,24 @@ struct NotesApp {
     sort_mode: SortMode,
     status: String,
     errors: Vec<String>,
+    search_query: String,
+    search_index: BTreeMap<String, BTreeSet<usize>>,
+    filter_query: String,
+    link_graph: LinkGraph,
+    watcher: Option<RecommendedWatcher>,
+    fs_events: Option<Receiver<notify::Result<notify::Event>>>,
+    recent_writes: HashMap<String, Instant>,
+    log_input: String,
+    query_input: String,
+    base: PathBuf,
+    root: PathBuf,
+    vaults: Vec<String>,
+    current_vault: Option<String>,
 }
 
 impl NotesApp {
     fn load() -> Self {
+        let base = resolve_root();
         let mut app = Self {
             notes: Vec::new(),
             selected_index: None,
@@ -56,41 +263,229 @@ impl NotesApp {
             sort_mode: SortMode::Recent,
             status: String::new(),
             errors: Vec::new(),
+            search_query: String::new(),
+            search_index: BTreeMap::new(),
+            filter_query: String::new(),
+            link_graph: LinkGraph::default(),
+            watcher: None,
+            fs_events: None,
+            recent_writes: HashMap::new(),
+            log_input: "30m".to_string(),
+            query_input: String::new(),
+            root: base.clone(),
+            base,
+            vaults: Vec::new(),
+            current_vault: None,
         };
 
-        if let Err(err) = fs::create_dir_all(NOTES_DIR) {
-            app.errors
+        app.discover_vaults();
+        app.reload();
+        app.start_watcher();
+        app
+    }
+
+    /// (Re)read all notes under the active vault root, recursing into category
+    /// subfolders when a vault is selected. Clears any previous load errors first.
+    fn reload(&mut self) {
+        self.notes.clear();
+        self.errors.clear();
+
+        if let Err(err) = fs::create_dir_all(&self.root) {
+            self.errors
                 .push(format!("Failed to create notes directory: {err}"));
-            return app;
+            return;
         }
 
-        let entries = match fs::read_dir(NOTES_DIR) {
+        let root = self.root.clone();
+        // Immediate subdirectories of `base` are switchable vaults, not
+        // categories, so the base ("All") view lists only its own loose notes.
+        // Inside a selected vault, nested subfolders are categories and are
+        // gathered recursively.
+        let recurse = self.current_vault.is_some();
+        self.collect_notes(&root, recurse);
+
+        self.sort_notes();
+        self.selected_index = None;
+        if !self.notes.is_empty() {
+            self.select_note(0);
+        }
+    }
+
+    /// Gather `.md` notes under `dir`, recording parse failures in `errors`.
+    /// When `recurse` is set, descends into category subfolders.
+    fn collect_notes(&mut self, dir: &Path, recurse: bool) {
+        let entries = match fs::read_dir(dir) {
             Ok(entries) => entries,
             Err(err) => {
-                app.errors
+                self.errors
                     .push(format!("Failed to read notes directory: {err}"));
-                return app;
+                return;
             }
         };
-
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
-                continue;
+            if path.is_dir() {
+                if recurse {
+                    self.collect_notes(&path, recurse);
+                }
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some("md") {
+                match parse_note_file(&path) {
+                    Ok(note) => self.notes.push(note),
+                    Err(err) => self
+                        .errors
+                        .push(format!("Failed to load note ({}): {err}", path.display())),
+                }
+            }
+        }
+    }
+
+    /// Discover the switchable vaults: every immediate subdirectory of `base`.
+    fn discover_vaults(&mut self) {
+        self.vaults.clear();
+        if let Ok(entries) = fs::read_dir(&self.base) {
+            for entry in entries.flatten() {
+                if entry.path().is_dir() {
+                    if let Some(name) = entry.file_name().to_str() {
+                        self.vaults.push(name.to_string());
+                    }
+                }
             }
-            match parse_note_file(&path) {
-                Ok(note) => app.notes.push(note),
-                Err(err) => app
-                    .errors
-                    .push(format!("Failed to load note ({}): {err}", path.display())),
+        }
+        self.vaults.sort();
+    }
+
+    /// Switch to a named vault (or back to the base root when `None`), reloading
+    /// notes and re-pointing the filesystem watcher at the new root.
+    fn switch_vault(&mut self, vault: Option<String>) {
+        self.root = match &vault {
+            Some(name) => self.base.join(name),
+            None => self.base.clone(),
+        };
+        self.current_vault = vault;
+        self.clear_selection();
+        self.reload();
+        self.start_watcher();
+    }
+
+    /// The category (relative subfolder) a note lives in within the vault root,
+    /// or an empty string when it sits at the top level.
+    fn category_of(&self, note: &Note) -> String {
+        note.file_path
+            .strip_prefix(&self.root)
+            .ok()
+            .and_then(|rel| rel.parent())
+            .map(|parent| parent.to_string_lossy().to_string())
+            .filter(|category| !category.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Spawn a watcher on the active vault root whose events are delivered over
+    /// a channel and drained in `update`. It is recursive inside a selected
+    /// vault and non-recursive on the base ("All") root.
+    fn start_watcher(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        match notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        }) {
+            Ok(mut watcher) => {
+                let mode = if self.current_vault.is_some() {
+                    RecursiveMode::Recursive
+                } else {
+                    RecursiveMode::NonRecursive
+                };
+                match watcher.watch(&self.root, mode) {
+                    Ok(()) => {
+                        self.watcher = Some(watcher);
+                        self.fs_events = Some(rx);
+                    }
+                    Err(err) => self
+                        .errors
+                        .push(format!("Failed to watch notes directory: {err}")),
+                }
             }
+            Err(err) => self
+                .errors
+                .push(format!("Failed to start filesystem watcher: {err}")),
         }
+    }
 
-        app.sort_notes();
-        if !app.notes.is_empty() {
-            app.select_note(0);
+    /// Record that the app just wrote `path`, so the resulting watcher event is
+    /// recognised as an echo of our own write. Keyed on the full path (like
+    /// [`same_file`]) so a write to one note never suppresses an external change
+    /// to a different note that merely shares a basename.
+    fn mark_written(&mut self, path: &Path) {
+        self.recent_writes.insert(path_key(path), Instant::now());
+    }
+
+    fn recently_written(&mut self, path: &Path) -> bool {
+        self.recent_writes
+            .retain(|_, at| at.elapsed() < WRITE_DEBOUNCE);
+        self.recent_writes.contains_key(&path_key(path))
+    }
+
+    /// Drain pending watcher events and reconcile `notes` with the files on
+    /// disk: reload changed `.md` files, drop removed ones, add new ones.
+    fn process_fs_events(&mut self) {
+        let events: Vec<notify::Result<notify::Event>> = match &self.fs_events {
+            Some(rx) => rx.try_iter().collect(),
+            None => return,
+        };
+        if events.is_empty() {
+            return;
+        }
+
+        let mut changed = false;
+        for result in events {
+            let event = match result {
+                Ok(event) => event,
+                Err(err) => {
+                    self.errors.push(format!("Watch error: {err}"));
+                    continue;
+                }
+            };
+            for path in event.paths {
+                if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+                    continue;
+                }
+                if self.recently_written(&path) {
+                    continue;
+                }
+                if path.exists() {
+                    match parse_note_file(&path) {
+                        Ok(note) => {
+                            if let Some(existing) = self
+                                .notes
+                                .iter_mut()
+                                .find(|n| same_file(&n.file_path, &path))
+                            {
+                                *existing = note;
+                            } else {
+                                self.notes.push(note);
+                            }
+                            changed = true;
+                        }
+                        Err(err) => self
+                            .errors
+                            .push(format!("Failed to load note ({}): {err}", path.display())),
+                    }
+                } else if let Some(pos) =
+                    self.notes.iter().position(|n| same_file(&n.file_path, &path))
+                {
+                    self.notes.remove(pos);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            let selected = self
+                .selected_index
+                .and_then(|idx| self.notes.get(idx))
+                .map(|note| note.file_path.clone());
+            self.sort_notes();
+            self.selected_index = selected
+                .and_then(|path| self.notes.iter().position(|n| same_file(&n.file_path, &path)));
         }
-        app
     }
 
     fn sort_notes(&mut self) {
@@ -104,7 +499,92 @@ impl NotesApp {
                 }
             }),
             SortMode::Title => self.notes.sort_by(|a, b| a.meta.title.cmp(&b.meta.title)),
+            SortMode::Priority => self.notes.sort_by(|a, b| {
+                let a_rank = a.meta.priority.map(Priority::rank).unwrap_or(0);
+                let b_rank = b.meta.priority.map(Priority::rank).unwrap_or(0);
+                b_rank
+                    .cmp(&a_rank)
+                    .then_with(|| a.meta.title.cmp(&b.meta.title))
+            }),
         }
+        self.rebuild_index();
+        self.link_graph = LinkGraph::build(&self.notes);
+    }
+
+    /// Rebuild the inverted index from scratch. Note indices shift on
+    /// sort/delete, so the postings are rebuilt rather than remapped.
+    fn rebuild_index(&mut self) {
+        self.search_index.clear();
+        for (index, note) in self.notes.iter().enumerate() {
+            for term in index_terms(note) {
+                self.search_index.entry(term).or_default().insert(index);
+            }
+        }
+    }
+
+    /// Run an AND query over the inverted index, returning matching note
+    /// indices ranked by weighted term occurrences: each title occurrence
+    /// counts 3, each tag occurrence 2, each body occurrence 1.
+    fn search(&self, query: &str) -> Vec<usize> {
+        let terms = tokenize(query);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let mut matches: Option<BTreeSet<usize>> = None;
+        for term in &terms {
+            let posting = self.search_index.get(term).cloned().unwrap_or_default();
+            matches = Some(match matches {
+                Some(acc) => acc.intersection(&posting).copied().collect(),
+                None => posting,
+            });
+        }
+        let mut ranked: Vec<(usize, usize)> = matches
+            .unwrap_or_default()
+            .into_iter()
+            .map(|index| (index, self.search_score(index, &terms)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.into_iter().map(|(index, _)| index).collect()
+    }
+
+    fn search_score(&self, index: usize, terms: &[String]) -> usize {
+        let note = &self.notes[index];
+        let title_terms = tokenize(&note.meta.title);
+        let tag_terms: Vec<String> = note.meta.tags.iter().flat_map(|t| tokenize(t)).collect();
+        let body_terms = tokenize(&note.body);
+        let mut score = 0;
+        for term in terms {
+            score += title_terms.iter().filter(|t| *t == term).count() * 3;
+            score += tag_terms.iter().filter(|t| *t == term).count() * 2;
+            score += body_terms.iter().filter(|t| *t == term).count();
+        }
+        score
+    }
+
+    /// Indices of notes whose title, tags, or body contain `filter_query` as a
+    /// case-insensitive subsequence. The currently selected note is always kept
+    /// visible so typing in the filter never interrupts an in-progress edit.
+    fn filtered_indices(&self) -> Vec<usize> {
+        let query = self.filter_query.trim().to_lowercase();
+        (0..self.notes.len())
+            .filter(|&index| {
+                if Some(index) == self.selected_index {
+                    return true;
+                }
+                if query.is_empty() {
+                    return true;
+                }
+                let note = &self.notes[index];
+                let haystacks = [
+                    note.meta.title.to_lowercase(),
+                    note.meta.tags.join(" ").to_lowercase(),
+                    note.body.to_lowercase(),
+                ];
+                haystacks
+                    .iter()
+                    .any(|hay| is_subsequence(&query, hay))
+            })
+            .collect()
     }
 
     fn select_note(&mut self, index: usize) {
@@ -115,6 +595,7 @@ impl NotesApp {
         self.selected_index = Some(index);
         let note = &self.notes[index];
         self.draft.body = note.body.clone();
+        self.draft.time_entries = note.meta.time_entries.clone();
     }
 
     fn clear_selection(&mut self) {
@@ -123,18 +604,27 @@ impl NotesApp {
     }
 
     fn save_current(&mut self) {
-        let (title, tags) = parse_title_and_tags(&self.draft.body);
+        let now = Local::now();
+        let (title, tags, priority, body_logs) =
+            parse_draft_fields(&self.draft.body, now.date_naive());
         if title.is_empty() {
             self.status = "Add a '# Title' line to save.".to_string();
             return;
         }
-
-        let now = Local::now();
+        // Fold any `log:` lines typed into the body into the persisted entries,
+        // then strip them so they are not re-counted (and re-dated) on resave.
+        if !body_logs.is_empty() {
+            self.draft.time_entries.extend(body_logs);
+            self.draft.body = strip_log_lines(&self.draft.body);
+        }
+        let time_entries = self.draft.time_entries.clone();
 
         let (meta, body, file_path) = if let Some(index) = self.selected_index {
             let existing = &mut self.notes[index];
             existing.meta.title = title.clone();
             existing.meta.tags = tags;
+            existing.meta.priority = priority;
+            existing.meta.time_entries = time_entries;
             existing.meta.updated_at = now.to_rfc3339();
             existing.body = self.draft.body.clone();
             (
@@ -152,6 +642,8 @@ impl NotesApp {
                     tags,
                     created_at: created_at.clone(),
                     updated_at: created_at,
+                    priority,
+                    time_entries,
                 },
                 self.draft.body.clone(),
                 PathBuf::new(),
@@ -164,24 +656,28 @@ impl NotesApp {
             .next()
             .unwrap_or("unknown-date");
         let slug = slugify(&meta.title);
-        let new_path = Path::new(NOTES_DIR).join(format!("{date}-{slug}.md"));
+        let new_path = self.root.join(format!("{date}-{slug}.md"));
 
         if !file_path.as_os_str().is_empty() && file_path != new_path {
             if let Err(err) = fs::rename(&file_path, &new_path) {
                 self.status = format!("Failed to rename file: {err}");
                 return;
             }
+            self.mark_written(&file_path);
         }
 
         if let Err(err) = write_note_file(&new_path, &meta, &body) {
             self.status = format!("Failed to save: {err}");
             return;
         }
+        self.mark_written(&new_path);
 
         if let Some(index) = self.selected_index {
             self.notes[index].meta = meta;
             self.notes[index].body = body;
             self.notes[index].file_path = new_path;
+            self.rebuild_index();
+            self.link_graph = LinkGraph::build(&self.notes);
         } else {
             self.notes.push(Note {
                 meta,
@@ -207,13 +703,16 @@ impl NotesApp {
             return;
         };
 
-        let note = &self.notes[index];
-        if let Err(err) = fs::remove_file(&note.file_path) {
+        let note_path = self.notes[index].file_path.clone();
+        if let Err(err) = fs::remove_file(&note_path) {
             self.status = format!("Failed to delete: {err}");
             return;
         }
+        self.mark_written(&note_path);
 
         self.notes.remove(index);
+        self.rebuild_index();
+        self.link_graph = LinkGraph::build(&self.notes);
         self.clear_selection();
         self.status = "Deleted.".to_string();
     }
@@ -236,18 +735,7 @@ impl NotesApp {
     }
 
     fn backlinks_for(&self, title: &str) -> Vec<String> {
-        let link_pattern = format!(r"\[\[{}\]\]", regex::escape(title));
-        let regex = Regex::new(&link_pattern).unwrap_or_else(|_| Regex::new("$^").unwrap());
-        let mut links = Vec::new();
-        for note in &self.notes {
-            if note.meta.title == title {
-                continue;
-            }
-            if regex.is_match(&note.body) {
-                links.push(note.meta.title.clone());
-            }
-        }
-        links
+        self.link_graph.inbound(title)
     }
 
     fn tag_cloud(&self) -> BTreeMap<String, usize> {
@@ -264,11 +752,39 @@ impl NotesApp {
 impl eframe::App for NotesApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.set_visuals(egui::Visuals::dark());
+        self.process_fs_events();
+        // Poll the watcher channel even while the UI is otherwise idle.
+        ctx.request_repaint_after(Duration::from_millis(500));
         egui::TopBottomPanel::top("top_bar").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 if ui.button("New Note").clicked() {
                     self.clear_selection();
                 }
+                let mut switch_to: Option<Option<String>> = None;
+                egui::ComboBox::from_id_source("vault_selector")
+                    .selected_text(self.current_vault.as_deref().unwrap_or("All"))
+                    .show_ui(ui, |ui| {
+                        if ui
+                            .selectable_label(self.current_vault.is_none(), "All")
+                            .clicked()
+                        {
+                            switch_to = Some(None);
+                        }
+                        for vault in &self.vaults {
+                            if ui
+                                .selectable_label(
+                                    self.current_vault.as_deref() == Some(vault.as_str()),
+                                    vault,
+                                )
+                                .clicked()
+                            {
+                                switch_to = Some(Some(vault.clone()));
+                            }
+                        }
+                    });
+                if let Some(vault) = switch_to {
+                    self.switch_vault(vault);
+                }
                 if ui.button("Save").clicked() {
                     self.save_current();
                 }
@@ -276,8 +792,90 @@ impl eframe::App for NotesApp {
                     self.delete_current();
                 }
                 ui.separator();
+                let current_priority =
+                    parse_draft_fields(&self.draft.body, Local::now().date_naive()).2;
+                let priority_label = current_priority.map(|p| p.label()).unwrap_or("None");
+                if ui.button(format!("Priority: {priority_label}")).clicked() {
+                    let next = current_priority.map(Priority::next).unwrap_or(Priority::Low);
+                    set_directive_line(&mut self.draft.body, "priority:", next.label());
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.log_input)
+                        .desired_width(52.0)
+                        .hint_text("1h30m"),
+                );
+                if ui.button("Log time").clicked() {
+                    if let Some((hours, minutes)) = parse_log_duration(&self.log_input) {
+                        self.draft.time_entries.push(TimeEntry::new(
+                            Local::now().date_naive(),
+                            hours,
+                            minutes,
+                        ));
+                        self.status = "Added time entry.".to_string();
+                    } else {
+                        self.status = "Could not parse duration (try 1h30m).".to_string();
+                    }
+                }
+                ui.separator();
+                egui::ComboBox::from_id_source("sort_mode")
+                    .selected_text(match self.sort_mode {
+                        SortMode::Recent => "Recent",
+                        SortMode::Title => "Title",
+                        SortMode::Priority => "Priority",
+                    })
+                    .show_ui(ui, |ui| {
+                        let mut changed = false;
+                        changed |= ui
+                            .selectable_value(&mut self.sort_mode, SortMode::Recent, "Recent")
+                            .clicked();
+                        changed |= ui
+                            .selectable_value(&mut self.sort_mode, SortMode::Title, "Title")
+                            .clicked();
+                        changed |= ui
+                            .selectable_value(&mut self.sort_mode, SortMode::Priority, "Priority")
+                            .clicked();
+                        if changed {
+                            let selected = self
+                                .selected_index
+                                .and_then(|idx| self.notes.get(idx))
+                                .map(|note| note.file_path.clone());
+                            self.sort_notes();
+                            self.selected_index = selected.and_then(|path| {
+                                self.notes.iter().position(|n| same_file(&n.file_path, &path))
+                            });
+                        }
+                    });
+                ui.separator();
+                ui.label("Query");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.query_input)
+                        .desired_width(160.0)
+                        .hint_text("tag:rust AND NOT body:\"draft\""),
+                );
+                ui.separator();
+                ui.label("Search");
+                ui.text_edit_singleline(&mut self.search_query);
+                ui.separator();
+                ui.label("Filter");
+                ui.text_edit_singleline(&mut self.filter_query);
+                ui.separator();
                 ui.label("Note");
                 let mut picked_index = None;
+                let visible: Vec<usize> = if !self.query_input.trim().is_empty() {
+                    match parse_query(&self.query_input) {
+                        Ok(query) => (0..self.notes.len())
+                            .filter(|&index| query.eval(&self.notes[index]))
+                            .collect(),
+                        Err(err) => {
+                            self.status = format!("Query error: {err}");
+                            (0..self.notes.len()).collect()
+                        }
+                    }
+                } else if !self.search_query.trim().is_empty() {
+                    self.search(&self.search_query)
+                } else {
+                    self.filtered_indices()
+                };
                 egui::ComboBox::from_id_source("note_selector")
                     .selected_text(
                         self.selected_index
@@ -286,12 +884,36 @@ impl eframe::App for NotesApp {
                             .unwrap_or("Untitled"),
                     )
                     .show_ui(ui, |ui| {
-                        for (index, note) in self.notes.iter().enumerate() {
-                            if ui
-                                .selectable_label(self.selected_index == Some(index), &note.meta.title)
-                                .clicked()
-                            {
-                                picked_index = Some(index);
+                        let mut categories: Vec<String> = Vec::new();
+                        for &index in &visible {
+                            let category = self.category_of(&self.notes[index]);
+                            if !categories.contains(&category) {
+                                categories.push(category);
+                            }
+                        }
+                        for category in &categories {
+                            if !category.is_empty() {
+                                ui.label(format!("— {category} —"));
+                            }
+                            for &index in &visible {
+                                if self.category_of(&self.notes[index]) != *category {
+                                    continue;
+                                }
+                                let note = &self.notes[index];
+                                let mut label = note.meta.title.clone();
+                                if let Some(priority) = note.meta.priority {
+                                    label = format!("[{}] {label}", priority.label());
+                                }
+                                let (hours, minutes) = total_logged(&note.meta.time_entries);
+                                if hours > 0 || minutes > 0 {
+                                    label = format!("{label} ({hours}h{minutes}m)");
+                                }
+                                if ui
+                                    .selectable_label(self.selected_index == Some(index), label)
+                                    .clicked()
+                                {
+                                    picked_index = Some(index);
+                                }
                             }
                         }
                     });
@@ -305,6 +927,34 @@ impl eframe::App for NotesApp {
             });
         });
 
+        let selected_title = self
+            .selected_index
+            .and_then(|idx| self.notes.get(idx))
+            .map(|note| note.meta.title.clone());
+        let outbound = selected_title
+            .as_deref()
+            .map(|title| self.link_graph.outbound(title))
+            .unwrap_or_default();
+        let inbound = selected_title
+            .as_deref()
+            .map(|title| self.backlinks_for(title))
+            .unwrap_or_default();
+        let broken = self.link_graph.broken_links(&self.draft.body);
+        let orphans = self.link_graph.orphans();
+        let mut nav_target: Option<String> = None;
+
+        egui::SidePanel::right("orphans_panel").show(ctx, |ui| {
+            ui.heading("Orphans");
+            if orphans.is_empty() {
+                ui.label("None");
+            }
+            for title in &orphans {
+                if ui.link(title).clicked() {
+                    nav_target = Some(title.clone());
+                }
+            }
+        });
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.columns(2, |columns| {
                 columns[0].heading("Editor");
@@ -325,9 +975,42 @@ impl eframe::App for NotesApp {
                         CommonMarkViewer::new()
                             .show(ui, &mut self.markdown_cache, &self.draft.body);
                     });
+
+                let ui = &mut columns[1];
+                if !outbound.is_empty() {
+                    ui.separator();
+                    ui.label("Links to");
+                    for title in &outbound {
+                        if ui.link(title).clicked() {
+                            nav_target = Some(title.clone());
+                        }
+                    }
+                }
+                if !inbound.is_empty() {
+                    ui.separator();
+                    ui.label("Linked from");
+                    for title in &inbound {
+                        if ui.link(title).clicked() {
+                            nav_target = Some(title.clone());
+                        }
+                    }
+                }
+                if !broken.is_empty() {
+                    ui.separator();
+                    ui.colored_label(egui::Color32::LIGHT_RED, "Broken links");
+                    for title in &broken {
+                        ui.colored_label(egui::Color32::LIGHT_RED, title);
+                    }
+                }
             });
         });
 
+        if let Some(title) = nav_target {
+            if let Some(index) = self.notes.iter().position(|note| note.meta.title == title) {
+                self.select_note(index);
+            }
+        }
+
         if !self.errors.is_empty() {
             egui::Window::new("Load Errors").show(ctx, |ui| {
                 for err in &self.errors {
@@ -389,20 +1072,192 @@ fn parse_tags(tags: &str) -> Vec<String> {
     set.into_iter().collect()
 }
 
-fn parse_title_and_tags(body: &str) -> (String, Vec<String>) {
+/// Pull the note's structured fields out of the draft body: the `# Title`
+/// heading, a `tags:` line, an optional `priority:` line, and any `log: 1h30m`
+/// directives (each logged against `today`). The returned log durations are the
+/// newly-typed ones; `save_current` appends them to the persisted time entries
+/// and strips the consumed lines from the body so they are not re-counted.
+fn parse_draft_fields(
+    body: &str,
+    today: NaiveDate,
+) -> (String, Vec<String>, Option<Priority>, Vec<TimeEntry>) {
     let mut title = String::new();
     let mut tags = Vec::new();
+    let mut priority = None;
+    let mut time_entries = Vec::new();
     for line in body.lines() {
         let trimmed = line.trim();
         if title.is_empty() && trimmed.starts_with("# ") {
             title = trimmed[2..].trim().to_string();
         }
-        if trimmed.to_lowercase().starts_with("tags:") {
-            let rest = trimmed[5..].trim();
-            tags = parse_tags(rest);
+        let lowered = trimmed.to_lowercase();
+        if lowered.starts_with("tags:") {
+            tags = parse_tags(trimmed[5..].trim());
+        } else if lowered.starts_with("priority:") {
+            priority = Priority::parse(&trimmed[9..]);
+        } else if lowered.starts_with("log:") {
+            if let Some((hours, minutes)) = parse_log_duration(&trimmed[4..]) {
+                time_entries.push(TimeEntry::new(today, hours, minutes));
+            }
+        }
+    }
+    (title, tags, priority, time_entries)
+}
+
+/// Remove any `log:` directive lines from a note body once they have been
+/// folded into the persisted time entries.
+fn strip_log_lines(body: &str) -> String {
+    let mut out = String::new();
+    for line in body.lines() {
+        if line.trim().to_lowercase().starts_with("log:") {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Parse a logged duration such as `1h30m`, `90m`, `2h`, or a bare minute
+/// count into `(hours, minutes)`.
+fn parse_log_duration(value: &str) -> Option<(u16, u16)> {
+    let mut hours = 0u16;
+    let mut minutes = 0u16;
+    let mut number = String::new();
+    for ch in value.chars() {
+        if ch.is_ascii_digit() {
+            number.push(ch);
+        } else if ch == 'h' || ch == 'H' {
+            hours += number.parse().ok()?;
+            number.clear();
+        } else if ch == 'm' || ch == 'M' {
+            minutes += number.parse().ok()?;
+            number.clear();
+        } else if ch.is_whitespace() {
+            continue;
+        } else {
+            return None;
+        }
+    }
+    if !number.is_empty() {
+        minutes += number.parse().ok()?;
+    }
+    if hours == 0 && minutes == 0 {
+        None
+    } else {
+        Some((hours, minutes))
+    }
+}
+
+/// Sum a note's logged time into normalized `(hours, minutes)`.
+fn total_logged(entries: &[TimeEntry]) -> (u64, u64) {
+    let total: u64 = entries
+        .iter()
+        .map(|e| e.hours as u64 * 60 + e.minutes as u64)
+        .sum();
+    (total / 60, total % 60)
+}
+
+/// Insert or replace a `key value` directive line in the draft body.
+fn set_directive_line(body: &mut String, key: &str, value: &str) {
+    let lowered_key = key.to_lowercase();
+    let mut replaced = false;
+    let mut out = String::new();
+    for line in body.lines() {
+        if line.trim().to_lowercase().starts_with(&lowered_key) {
+            out.push_str(&format!("{key} {value}"));
+            replaced = true;
+        } else {
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    if !replaced {
+        out.push_str(&format!("{key} {value}\n"));
+    }
+    *body = out;
+}
+
+/// Resolve the storage root at startup, preferring `$XDG_DATA_HOME/cluster/notes`,
+/// falling back to `~/.local/share/cluster/notes`, and finally to `./notes`.
+fn resolve_root() -> PathBuf {
+    if let Some(xdg) = std::env::var_os("XDG_DATA_HOME") {
+        if !xdg.is_empty() {
+            return PathBuf::from(xdg).join("cluster").join("notes");
+        }
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        if !home.is_empty() {
+            return PathBuf::from(home)
+                .join(".local")
+                .join("share")
+                .join("cluster")
+                .join("notes");
         }
     }
-    (title, tags)
+    PathBuf::from(NOTES_DIR)
+}
+
+/// Compare two note paths by their full path, canonicalizing when both files
+/// still exist so stored paths and watcher event paths line up. Comparing whole
+/// paths (not just file names) keeps notes that share a basename across category
+/// folders or vaults — e.g. `work/2024-01-01-note.md` vs `personal/…` — distinct.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// A stable map key for a path: its canonical form when the file exists,
+/// falling back to the lexical path otherwise (e.g. after a delete).
+fn path_key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The shared `[[target]]` wiki-link pattern, compiled once.
+fn link_regex() -> &'static Regex {
+    static LINK_REGEX: OnceLock<Regex> = OnceLock::new();
+    LINK_REGEX.get_or_init(|| Regex::new(r"\[\[([^\[\]]+)\]\]").unwrap())
+}
+
+/// Parse every `[[target]]` wiki-link target out of a note body.
+fn parse_links(body: &str) -> Vec<String> {
+    link_regex()
+        .captures_iter(body)
+        .map(|cap| cap[1].trim().to_string())
+        .collect()
+}
+
+/// Subsequence test: every char of `needle` appears in `haystack` in order. A
+/// plain substring match is a special case of this. Chars are compared exactly,
+/// so callers lowercase both sides for a case-insensitive match.
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut chars = haystack.chars();
+    needle
+        .chars()
+        .all(|want| chars.any(|have| have == want))
+}
+
+/// Split text into lowercased alphanumeric terms for indexing and querying.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+/// Collect the terms that index a note: title, tags, and body.
+fn index_terms(note: &Note) -> Vec<String> {
+    let mut terms = tokenize(&note.meta.title);
+    for tag in &note.meta.tags {
+        terms.extend(tokenize(tag));
+    }
+    terms.extend(tokenize(&note.body));
+    terms
 }
 
 fn slugify(title: &str) -> String {
@@ -434,6 +1289,218 @@ fn parse_datetime(value: &str) -> Option<DateTime<FixedOffset>> {
     DateTime::parse_from_rfc3339(value).ok()
 }
 
+/// A compiled filter clause produced by [`parse_query`].
+enum Query {
+    Tag(String),
+    TitleMatch(Regex),
+    CreatedAfter(DateTime<FixedOffset>),
+    BodyContains(String),
+    LinksTo(String),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+}
+
+impl Query {
+    /// Evaluate the query against a single note.
+    fn eval(&self, note: &Note) -> bool {
+        match self {
+            Query::Tag(tag) => note
+                .meta
+                .tags
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(tag)),
+            Query::TitleMatch(regex) => regex.is_match(&note.meta.title),
+            Query::CreatedAfter(threshold) => parse_datetime(&note.meta.created_at)
+                .map(|created| created > *threshold)
+                .unwrap_or(false),
+            Query::BodyContains(phrase) => {
+                note.body.to_lowercase().contains(&phrase.to_lowercase())
+            }
+            Query::LinksTo(title) => note.body.contains(&format!("[[{title}]]")),
+            Query::And(lhs, rhs) => lhs.eval(note) && rhs.eval(note),
+            Query::Or(lhs, rhs) => lhs.eval(note) || rhs.eval(note),
+            Query::Not(inner) => !inner.eval(note),
+        }
+    }
+}
+
+enum QueryToken {
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+    Clause(String),
+}
+
+/// Parse a query string such as `tag:rust AND NOT body:"draft"` into a [`Query`].
+fn parse_query(input: &str) -> Result<Query, String> {
+    let tokens = tokenize_query(input)?;
+    if tokens.is_empty() {
+        return Err("Empty query.".to_string());
+    }
+    let mut parser = QueryParser { tokens, pos: 0 };
+    let query = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err("Unexpected trailing tokens.".to_string());
+    }
+    Ok(query)
+}
+
+fn tokenize_query(input: &str) -> Result<Vec<QueryToken>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if ch == '(' {
+            tokens.push(QueryToken::LParen);
+            i += 1;
+            continue;
+        }
+        if ch == ')' {
+            tokens.push(QueryToken::RParen);
+            i += 1;
+            continue;
+        }
+        let mut term = String::new();
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '"' {
+                term.push(c);
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    term.push(chars[i]);
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err("Unterminated quoted string.".to_string());
+                }
+                term.push('"');
+                i += 1;
+                continue;
+            }
+            if c.is_whitespace() || c == '(' || c == ')' {
+                break;
+            }
+            term.push(c);
+            i += 1;
+        }
+        match term.to_uppercase().as_str() {
+            "AND" => tokens.push(QueryToken::And),
+            "OR" => tokens.push(QueryToken::Or),
+            "NOT" => tokens.push(QueryToken::Not),
+            _ => tokens.push(QueryToken::Clause(term)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct QueryParser {
+    tokens: Vec<QueryToken>,
+    pos: usize,
+}
+
+impl QueryParser {
+    fn parse_or(&mut self) -> Result<Query, String> {
+        let mut left = self.parse_and()?;
+        while matches!(self.tokens.get(self.pos), Some(QueryToken::Or)) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Query::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, String> {
+        let mut left = self.parse_not()?;
+        while matches!(self.tokens.get(self.pos), Some(QueryToken::And)) {
+            self.pos += 1;
+            let right = self.parse_not()?;
+            left = Query::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Query, String> {
+        if matches!(self.tokens.get(self.pos), Some(QueryToken::Not)) {
+            self.pos += 1;
+            let inner = self.parse_not()?;
+            return Ok(Query::Not(Box::new(inner)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Query, String> {
+        match self.tokens.get(self.pos) {
+            Some(QueryToken::LParen) => {
+                self.pos += 1;
+                let inner = self.parse_or()?;
+                match self.tokens.get(self.pos) {
+                    Some(QueryToken::RParen) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err("Expected ')'.".to_string()),
+                }
+            }
+            Some(QueryToken::Clause(text)) => {
+                let text = text.clone();
+                self.pos += 1;
+                parse_clause(&text)
+            }
+            Some(_) => Err("Expected a clause.".to_string()),
+            None => Err("Unexpected end of query.".to_string()),
+        }
+    }
+}
+
+fn parse_clause(text: &str) -> Result<Query, String> {
+    let (field, value) = text
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid clause '{text}' (expected field:value)."))?;
+    match field.to_lowercase().as_str() {
+        "tag" => Ok(Query::Tag(value.to_string())),
+        "title" => {
+            let pattern = value
+                .strip_prefix('~')
+                .ok_or_else(|| "title: expects a ~regex.".to_string())?;
+            let regex = Regex::new(pattern).map_err(|err| format!("Invalid regex: {err}"))?;
+            Ok(Query::TitleMatch(regex))
+        }
+        "created" => {
+            let rest = value
+                .strip_prefix('>')
+                .ok_or_else(|| "created: expects >DATE.".to_string())?;
+            let threshold =
+                parse_query_datetime(rest).ok_or_else(|| format!("Invalid date '{rest}'."))?;
+            Ok(Query::CreatedAfter(threshold))
+        }
+        "body" => Ok(Query::BodyContains(value.trim_matches('"').to_string())),
+        "links" => Ok(Query::LinksTo(value.to_string())),
+        other => Err(format!("Unknown field '{other}'.")),
+    }
+}
+
+/// Parse a query date, accepting either a full RFC 3339 timestamp or a bare
+/// `YYYY-MM-DD` (interpreted as midnight UTC).
+fn parse_query_datetime(value: &str) -> Option<DateTime<FixedOffset>> {
+    if let Some(datetime) = parse_datetime(value) {
+        return Some(datetime);
+    }
+    let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()?;
+    let naive = date.and_hms_opt(0, 0, 0)?;
+    Some(DateTime::from_naive_utc_and_offset(
+        naive,
+        FixedOffset::east_opt(0)?,
+    ))
+}
+
 fn main() -> eframe::Result<()> {
     let options = eframe::NativeOptions::default();
     eframe::run_native(